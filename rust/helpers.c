@@ -7,6 +7,7 @@
 #include <linux/gfp.h>
 #include <linux/highmem.h>
 #include <linux/uio.h>
+#include <linux/compat.h>
 
 void rust_helper_BUG(void)
 {
@@ -105,6 +106,12 @@ size_t rust_helper_copy_to_iter(const void *addr, size_t bytes, struct iov_iter
 }
 EXPORT_SYMBOL_GPL(rust_helper_copy_to_iter);
 
+unsigned int rust_helper_iov_iter_rw(const struct iov_iter *i)
+{
+	return iov_iter_rw(i);
+}
+EXPORT_SYMBOL_GPL(rust_helper_iov_iter_rw);
+
 bool rust_helper_is_err(__force const void *ptr)
 {
 	return IS_ERR(ptr);
@@ -117,6 +124,14 @@ long rust_helper_ptr_err(__force const void *ptr)
 }
 EXPORT_SYMBOL_GPL(rust_helper_ptr_err);
 
+#ifdef CONFIG_COMPAT
+void __user *rust_helper_compat_ptr(compat_uptr_t uptr)
+{
+	return compat_ptr(uptr);
+}
+EXPORT_SYMBOL_GPL(rust_helper_compat_ptr);
+#endif
+
 #if !defined(CONFIG_ARM)
 // See https://github.com/rust-lang/rust-bindgen/issues/1671
 static_assert(__builtin_types_compatible_p(size_t, uintptr_t),
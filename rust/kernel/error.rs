@@ -53,6 +53,15 @@ impl Error {
     /// Interrupted system call.
     pub const EINTR: Self = Error(-(bindings::EINTR as i32));
 
+    /// No space left on device.
+    pub const ENOSPC: Self = Error(-(bindings::ENOSPC as i32));
+
+    /// Math result not representable.
+    pub const ERANGE: Self = Error(-(bindings::ERANGE as i32));
+
+    /// Operation is not supported.
+    pub const ENOTSUPP: Self = Error(-(bindings::ENOTSUPP as i32));
+
     /// Creates an [`Error`] from a kernel error code.
     pub fn from_kernel_errno(errno: c_types::c_int) -> Error {
         Error(errno)
@@ -62,10 +71,26 @@ impl Error {
     pub fn to_kernel_errno(&self) -> c_types::c_int {
         self.0
     }
-}
 
-impl fmt::Debug for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    /// Returns the symbolic name of the error (e.g. `EINVAL`), if known.
+    ///
+    /// The most common errors always have a name. Other names are only available with
+    /// `CONFIG_SYMBOLIC_ERRNAME`.
+    pub fn name(&self) -> Option<&'static CStr> {
+        let name = match *self {
+            Error::EINVAL => crate::c_str!("EINVAL"),
+            Error::EFAULT => crate::c_str!("EFAULT"),
+            Error::EINTR => crate::c_str!("EINTR"),
+            Error::EAGAIN => crate::c_str!("EAGAIN"),
+            Error::ENOMEM => crate::c_str!("ENOMEM"),
+            Error::ENOSPC => crate::c_str!("ENOSPC"),
+            Error::ENOTSUPP => crate::c_str!("ENOTSUPP"),
+            _ => return self.errname(),
+        };
+        Some(name)
+    }
+
+    fn errname(&self) -> Option<&'static CStr> {
         // SAFETY: FFI call.
         #[cfg(CONFIG_SYMBOLIC_ERRNAME)]
         let name = unsafe { crate::bindings::errname(-self.0) };
@@ -73,15 +98,35 @@ impl fmt::Debug for Error {
         let name: *const c_types::c_char = core::ptr::null();
 
         if name.is_null() {
-            // Print out number if no name can be found.
-            return f.debug_tuple("Error").field(&-self.0).finish();
+            return None;
         }
 
         // SAFETY: `'static` string from C, and is not NULL.
-        let cstr = unsafe { CStr::from_char_ptr(name) };
-        // SAFETY: These strings are ASCII-only.
-        let str = unsafe { str::from_utf8_unchecked(&cstr) };
-        f.debug_tuple(str).finish()
+        Some(unsafe { CStr::from_char_ptr(name) })
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            // SAFETY: These strings are ASCII-only.
+            Some(name) => f
+                .debug_tuple(unsafe { str::from_utf8_unchecked(name) })
+                .finish(),
+            // Print out number if no name can be found.
+            None => f.debug_tuple("Error").field(&-self.0).finish(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            // SAFETY: These strings are ASCII-only.
+            Some(name) => f.write_str(unsafe { str::from_utf8_unchecked(name) }),
+            // Print out number if no name can be found.
+            None => write!(f, "{}", -self.0),
+        }
     }
 }
 
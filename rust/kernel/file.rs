@@ -5,7 +5,8 @@
 //! C headers: [`include/linux/fs.h`](../../../../include/linux/fs.h) and
 //! [`include/linux/file.h`](../../../../include/linux/file.h)
 
-use crate::bindings;
+use crate::{bindings, error::Error, file_operations::SeekFrom, Result};
+use core::convert::{TryFrom, TryInto};
 
 /// Wraps the kernel's `struct file`.
 ///
@@ -33,9 +34,53 @@ impl File {
         unsafe { (*self.ptr).f_pos as u64 }
     }
 
+    /// Returns the flags associated with the file (`struct file::f_flags`).
+    ///
+    /// These are the `O_*` flags the file was opened with, e.g. `O_NONBLOCK`.
+    pub fn flags(&self) -> u32 {
+        // SAFETY: `File::ptr` is guaranteed to be valid by the type invariants.
+        unsafe { (*self.ptr).f_flags }
+    }
+
     /// Returns whether the file is in blocking mode.
     pub fn is_blocking(&self) -> bool {
-        // SAFETY: `File::ptr` is guaranteed to be valid by the type invariants.
-        unsafe { (*self.ptr).f_flags & bindings::O_NONBLOCK == 0 }
+        self.flags() & bindings::O_NONBLOCK == 0
+    }
+
+    /// Changes the position of a file whose contents are `size` bytes long.
+    ///
+    /// This is meant to be called from `FileOperations::seek` by devices of a fixed size. Seeking
+    /// before the start or past the end fails with `EINVAL`. On success, the file position is
+    /// updated and returned.
+    pub fn fixed_size_seek(&self, offset: SeekFrom, size: u64) -> Result<u64> {
+        let (offset, whence) = match offset {
+            SeekFrom::Start(off) => (off.try_into()?, bindings::SEEK_SET),
+            SeekFrom::Current(off) => (off, bindings::SEEK_CUR),
+            SeekFrom::End(off) => (off, bindings::SEEK_END),
+        };
+        // SAFETY: `File::ptr` is guaranteed to be valid by the type invariants, and
+        // `fixed_size_llseek` updates the position with the locking expected of `llseek`.
+        let ret = unsafe {
+            bindings::fixed_size_llseek(self.ptr as _, offset, whence as _, size.try_into()?)
+        };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret as _));
+        }
+        Ok(ret as u64)
+    }
+}
+
+/// Checks an access of `len` bytes at `offset` into a file whose contents are `size` bytes long.
+///
+/// This is meant to be used by `FileOperations::read` and `FileOperations::write` in devices backed
+/// by a buffer. Returns the number of bytes that can be accessed, which is `len` clamped at the end
+/// of the file (so `0` at or past it), or `EINVAL` if `offset + len` overflows.
+pub fn check_offset_len(offset: u64, len: usize, size: u64) -> Result<usize> {
+    let len_u64 = u64::try_from(len)?;
+    offset.checked_add(len_u64).ok_or(Error::EINVAL)?;
+    if offset >= size {
+        return Ok(0);
     }
+    // The result is at most `len`, so it fits in a `usize`.
+    Ok(len_u64.min(size - offset) as usize)
 }
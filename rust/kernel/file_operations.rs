@@ -16,11 +16,17 @@ use crate::{
     from_kernel_result,
     io_buffer::{IoBufferReader, IoBufferWriter},
     iov_iter::IovIter,
+    mm::VmArea,
     sync::CondVar,
     types::PointerWrapper,
     user_ptr::{UserSlicePtr, UserSlicePtrReader, UserSlicePtrWriter},
 };
 
+extern "C" {
+    #[cfg(CONFIG_COMPAT)]
+    fn rust_helper_compat_ptr(uptr: u32) -> *mut c_types::c_void;
+}
+
 /// Wraps the kernel's `struct poll_table_struct`.
 ///
 /// # Invariants
@@ -85,7 +91,7 @@ unsafe extern "C" fn open_callback<A: FileOpenAdapter, T: FileOpener<A::Arg>>(
 ) -> c_types::c_int {
     from_kernel_result! {
         let arg = A::convert(inode, file);
-        let ptr = T::open(&*arg)?.into_pointer();
+        let ptr = T::open(&*arg, &File::from_ptr(file))?.into_pointer();
         (*file).private_data = ptr as *mut c_types::c_void;
         Ok(0)
     }
@@ -103,6 +109,9 @@ unsafe extern "C" fn read_callback<T: FileOperations>(
         // No `FMODE_UNSIGNED_OFFSET` support, so `offset` must be in [0, 2^63).
         // See discussion in https://github.com/fishinabarrel/linux-kernel-module-rust/pull/113
         let read = f.read(&File::from_ptr(file), &mut data, (*offset).try_into()?)?;
+        if read > len {
+            return Err(Error::EINVAL);
+        }
         (*offset) += bindings::loff_t::try_from(read).unwrap();
         Ok(read as _)
     }
@@ -113,16 +122,51 @@ unsafe extern "C" fn read_iter_callback<T: FileOperations>(
     raw_iter: *mut bindings::iov_iter,
 ) -> isize {
     from_kernel_result! {
+        let len = (*raw_iter).count;
         let mut iter = IovIter::from_ptr(raw_iter);
         let file = (*iocb).ki_filp;
         let offset = (*iocb).ki_pos;
         let f = &*((*file).private_data as *const T);
         let read = f.read(&File::from_ptr(file), &mut iter, offset.try_into()?)?;
+        if read > len {
+            return Err(Error::EINVAL);
+        }
         (*iocb).ki_pos += bindings::loff_t::try_from(read).unwrap();
         Ok(read as _)
     }
 }
 
+unsafe extern "C" fn splice_read_callback<T: FileOperations>(
+    file: *mut bindings::file,
+    offset: *mut bindings::loff_t,
+    pipe: *mut bindings::pipe_inode_info,
+    len: c_types::c_size_t,
+    _flags: c_types::c_uint,
+) -> c_types::c_ssize_t {
+    from_kernel_result! {
+        let mut raw_iter = mem::MaybeUninit::<bindings::iov_iter>::uninit();
+        bindings::iov_iter_pipe(raw_iter.as_mut_ptr(), bindings::READ, pipe, len);
+        // SAFETY: `raw_iter` was initialized above and outlives `iter`.
+        let mut iter = IovIter::from_ptr(raw_iter.as_mut_ptr());
+        let f = &*((*file).private_data as *const T);
+        let pos = (*offset).try_into()?;
+        let res = match f.splice_read(&File::from_ptr(file), &mut iter, len, pos) {
+            Ok(read) if read > len => Err(Error::EINVAL),
+            res => res,
+        };
+        let read = match res {
+            Ok(read) => read,
+            Err(e) => {
+                // Release the pipe buffers that were filled before the error.
+                iter.revert(len - IoBufferWriter::len(&iter));
+                return Err(e);
+            }
+        };
+        (*offset) += bindings::loff_t::try_from(read).unwrap();
+        Ok(read as _)
+    }
+}
+
 unsafe extern "C" fn write_callback<T: FileOperations>(
     file: *mut bindings::file,
     buf: *const c_types::c_char,
@@ -135,6 +179,9 @@ unsafe extern "C" fn write_callback<T: FileOperations>(
         // No `FMODE_UNSIGNED_OFFSET` support, so `offset` must be in [0, 2^63).
         // See discussion in https://github.com/fishinabarrel/linux-kernel-module-rust/pull/113
         let written = f.write(&File::from_ptr(file), &mut data, (*offset).try_into()?)?;
+        if written > len {
+            return Err(Error::EINVAL);
+        }
         (*offset) += bindings::loff_t::try_from(written).unwrap();
         Ok(written as _)
     }
@@ -145,11 +192,15 @@ unsafe extern "C" fn write_iter_callback<T: FileOperations>(
     raw_iter: *mut bindings::iov_iter,
 ) -> isize {
     from_kernel_result! {
+        let len = (*raw_iter).count;
         let mut iter = IovIter::from_ptr(raw_iter);
         let file = (*iocb).ki_filp;
         let offset = (*iocb).ki_pos;
         let f = &*((*file).private_data as *const T);
         let written = f.write(&File::from_ptr(file), &mut iter, offset.try_into()?)?;
+        if written > len {
+            return Err(Error::EINVAL);
+        }
         (*iocb).ki_pos += bindings::loff_t::try_from(written).unwrap();
         Ok(written as _)
     }
@@ -203,6 +254,9 @@ unsafe extern "C" fn compat_ioctl_callback<T: FileOperations>(
 ) -> c_types::c_long {
     from_kernel_result! {
         let f = &*((*file).private_data as *const T);
+        // 32-bit user pointers must be converted, as the kernel's `compat_ptr_ioctl` does.
+        #[cfg(CONFIG_COMPAT)]
+        let arg = rust_helper_compat_ptr(arg as u32) as c_types::c_ulong;
         // SAFETY: This function is called by the kernel, so it must set `fs` appropriately.
         let mut cmd = IoctlCommand::new(cmd as _, arg as _);
         let ret = f.compat_ioctl(&File::from_ptr(file), &mut cmd)?;
@@ -216,7 +270,7 @@ unsafe extern "C" fn mmap_callback<T: FileOperations>(
 ) -> c_types::c_int {
     from_kernel_result! {
         let f = &*((*file).private_data as *const T);
-        f.mmap(&File::from_ptr(file), &mut *vma)?;
+        f.mmap(&File::from_ptr(file), &mut VmArea::from_ptr(vma))?;
         Ok(0)
     }
 }
@@ -313,7 +367,11 @@ impl<A: FileOpenAdapter, T: FileOpener<A::Arg>> FileOperationsVtable<A, T> {
         sendpage: None,
         setlease: None,
         show_fdinfo: None,
-        splice_read: None,
+        splice_read: if T::TO_USE.splice_read {
+            Some(splice_read_callback::<T>)
+        } else {
+            None
+        },
         splice_write: None,
         unlocked_ioctl: if T::TO_USE.ioctl {
             Some(unlocked_ioctl_callback::<T>)
@@ -345,6 +403,9 @@ pub struct ToUse {
     /// The `read_iter` field of [`struct file_operations`].
     pub read_iter: bool,
 
+    /// The `splice_read` field of [`struct file_operations`].
+    pub splice_read: bool,
+
     /// The `write` field of [`struct file_operations`].
     pub write: bool,
 
@@ -375,6 +436,7 @@ pub struct ToUse {
 pub const USE_NONE: ToUse = ToUse {
     read: false,
     read_iter: false,
+    splice_read: false,
     write: false,
     write_iter: false,
     seek: false,
@@ -460,12 +522,12 @@ impl IoctlCommand {
     /// It is meant to be used in implementations of [`FileOperations::ioctl`] and
     /// [`FileOperations::compat_ioctl`].
     pub fn dispatch<T: IoctlHandler>(&mut self, handler: &T, file: &File) -> Result<i32> {
-        let dir = (self.cmd >> bindings::_IOC_DIRSHIFT) & bindings::_IOC_DIRMASK;
+        let dir = self.direction();
         if dir == bindings::_IOC_NONE {
             return handler.pure(file, self.cmd, self.arg);
         }
 
-        let data = self.user_slice.take().ok_or(Error::EINVAL)?;
+        let data = self.user_slice()?;
         const READ_WRITE: u32 = bindings::_IOC_READ | bindings::_IOC_WRITE;
         match dir {
             bindings::_IOC_WRITE => handler.write(file, self.cmd, &mut data.reader()),
@@ -475,10 +537,41 @@ impl IoctlCommand {
         }
     }
 
+    /// Returns the user buffer of the command, sized according to its `_IOC_SIZE` bits.
+    ///
+    /// This is meant for handlers that need to both read and write the same buffer, and split it
+    /// themselves. The buffer can only be taken once, either by this function or by
+    /// [`IoctlCommand::dispatch`]; subsequent calls fail with `EINVAL`.
+    pub fn user_slice(&mut self) -> Result<UserSlicePtr> {
+        self.user_slice.take().ok_or(Error::EINVAL)
+    }
+
     /// Returns the raw 32-bit value of the command and the ptr-sized argument.
     pub fn raw(&self) -> (u32, usize) {
         (self.cmd, self.arg)
     }
+
+    /// Returns the direction of the buffer of the command, that is, the `_IOC_DIR` bits.
+    ///
+    /// The result is one of `_IOC_NONE`, `_IOC_READ`, `_IOC_WRITE`, or both of the latter.
+    pub fn direction(&self) -> u32 {
+        (self.cmd >> bindings::_IOC_DIRSHIFT) & bindings::_IOC_DIRMASK
+    }
+
+    /// Returns the size of the buffer of the command, that is, the `_IOC_SIZE` bits.
+    pub fn size(&self) -> usize {
+        ((self.cmd >> bindings::_IOC_SIZESHIFT) & bindings::_IOC_SIZEMASK) as _
+    }
+
+    /// Returns the type (or "magic") of the command, that is, the `_IOC_TYPE` bits.
+    pub fn kind(&self) -> u32 {
+        (self.cmd >> bindings::_IOC_TYPESHIFT) & bindings::_IOC_TYPEMASK
+    }
+
+    /// Returns the sequence number of the command, that is, the `_IOC_NR` bits.
+    pub fn number(&self) -> u32 {
+        (self.cmd >> bindings::_IOC_NRSHIFT) & bindings::_IOC_NRMASK
+    }
 }
 
 /// Trait for extracting file open arguments from kernel data structures.
@@ -510,12 +603,15 @@ pub trait FileOpenAdapter {
 pub trait FileOpener<T: ?Sized>: FileOperations {
     /// Creates a new instance of this file.
     ///
+    /// `context` is shared by all files opened through the same registration, while `file` is the
+    /// file being opened, which can be used to set up per-open state (e.g., based on its flags).
+    ///
     /// Corresponds to the `open` function pointer in `struct file_operations`.
-    fn open(context: &T) -> Result<Self::Wrapper>;
+    fn open(context: &T, file: &File) -> Result<Self::Wrapper>;
 }
 
 impl<T: FileOperations<Wrapper = Box<T>> + Default> FileOpener<()> for T {
-    fn open(_: &()) -> Result<Self::Wrapper> {
+    fn open(_: &(), _file: &File) -> Result<Self::Wrapper> {
         Ok(Box::try_new(T::default())?)
     }
 }
@@ -549,6 +645,22 @@ pub trait FileOperations: Send + Sync + Sized {
         Err(Error::EINVAL)
     }
 
+    /// Reads up to `len` bytes from this file at `offset` into a pipe, as in `splice()`.
+    ///
+    /// The data is written to `iter`, which is backed by the pipe. Returns the number of bytes
+    /// read; if an error is returned instead, any data already written to `iter` is discarded.
+    ///
+    /// Corresponds to the `splice_read` function pointer in `struct file_operations`.
+    fn splice_read(
+        &self,
+        _file: &File,
+        _iter: &mut IovIter,
+        _len: usize,
+        _offset: u64,
+    ) -> Result<usize> {
+        Err(Error::EINVAL)
+    }
+
     /// Writes data from the caller's buffer to this file.
     ///
     /// Corresponds to the `write` and `write_iter` function pointers in `struct file_operations`.
@@ -572,9 +684,13 @@ pub trait FileOperations: Send + Sync + Sized {
 
     /// Performs 32-bit IO control operations on that are specific to the file on 64-bit kernels.
     ///
+    /// By default, this is the same as [`FileOperations::ioctl`]. Implementations should override
+    /// it if 32-bit user space uses different command numbers or argument layouts. The argument of
+    /// `cmd` has already been converted with `compat_ptr`, so it can be used as a user pointer.
+    ///
     /// Corresponds to the `compat_ioctl` function pointer in `struct file_operations`.
-    fn compat_ioctl(&self, _file: &File, _cmd: &mut IoctlCommand) -> Result<i32> {
-        Err(Error::EINVAL)
+    fn compat_ioctl(&self, file: &File, cmd: &mut IoctlCommand) -> Result<i32> {
+        self.ioctl(file, cmd)
     }
 
     /// Syncs pending changes to this file.
@@ -587,8 +703,7 @@ pub trait FileOperations: Send + Sync + Sized {
     /// Maps areas of the caller's virtual memory with device/file memory.
     ///
     /// Corresponds to the `mmap` function pointer in `struct file_operations`.
-    /// TODO: wrap `vm_area_struct` so that we don't have to expose it.
-    fn mmap(&self, _file: &File, _vma: &mut bindings::vm_area_struct) -> Result {
+    fn mmap(&self, _file: &File, _vma: &mut VmArea) -> Result {
         Err(Error::EINVAL)
     }
 
@@ -23,15 +23,28 @@ extern "C" {
         bytes: usize,
         i: *mut bindings::iov_iter,
     ) -> usize;
+
+    fn rust_helper_iov_iter_rw(i: *const bindings::iov_iter) -> c_types::c_uint;
 }
 
 /// Wraps the kernel's `struct iov_iter`.
 ///
+/// Copies to and from the iterator (e.g., through [`IoBufferWriter::write_slice`] and
+/// [`IoBufferReader::read_slice`]) are all-or-nothing: if only part of the data can be copied, the
+/// iterator is moved back to where it was and `EFAULT` is returned.
+///
 /// # Invariants
 ///
 /// The pointer [`IovIter::ptr`] is non-null and valid.
+///
+/// An iterator set up for `READ` (i.e., for a read from the file) can only be written to, and one
+/// set up for `WRITE` can only be read from; using it in the other direction fails with `EINVAL`.
+///
+/// The iterator is never advanced past its end, i.e., by more than `common_len()` bytes, so the
+/// number of bytes consumed since construction is always `start_len - common_len()`.
 pub struct IovIter {
     ptr: *mut bindings::iov_iter,
+    start_len: usize,
 }
 
 impl IovIter {
@@ -40,6 +53,14 @@ impl IovIter {
         unsafe { (*self.ptr).count }
     }
 
+    fn check_direction(&self, direction: u32) -> Result {
+        // SAFETY: `IovIter::ptr` is guaranteed to be valid by the type invariants.
+        if unsafe { rust_helper_iov_iter_rw(self.ptr) } != direction {
+            return Err(Error::EINVAL);
+        }
+        Ok(())
+    }
+
     /// Constructs a new [`struct iov_iter`] wrapper.
     ///
     /// # Safety
@@ -47,7 +68,102 @@ impl IovIter {
     /// The pointer `ptr` must be non-null and valid for the lifetime of the object.
     pub(crate) unsafe fn from_ptr(ptr: *mut bindings::iov_iter) -> Self {
         // INVARIANTS: the safety contract ensures the type invariant will hold.
-        Self { ptr }
+        Self {
+            ptr,
+            start_len: (*ptr).count,
+        }
+    }
+
+    /// Returns `true` if no data is left in the iterator.
+    ///
+    /// Unlike [`IoBufferReader::is_empty`] and [`IoBufferWriter::is_empty`], this can be called
+    /// without disambiguating between the two traits.
+    pub fn is_empty(&self) -> bool {
+        self.common_len() == 0
+    }
+
+    /// Advances the iterator by `bytes` without copying any data.
+    ///
+    /// The amount is clamped to the number of bytes left in the iterator, so it never advances
+    /// past the end. Returns the number of bytes actually skipped, which is less than `bytes` if
+    /// fewer were left.
+    pub fn advance(&mut self, bytes: usize) -> usize {
+        // INVARIANTS: Clamping to the remaining length ensures we never move past the end.
+        let bytes = bytes.min(self.common_len());
+        // SAFETY: `IovIter::ptr` is guaranteed to be valid by the type invariants, and `bytes` is
+        // within the remaining length of the iterator.
+        unsafe { bindings::iov_iter_advance(self.ptr, bytes) };
+        bytes
+    }
+
+    /// Calls `f` on each contiguous segment of the data left in the iterator.
+    ///
+    /// Only iterators backed by kernel memory (`ITER_KVEC` and `ITER_BVEC`) can be walked this
+    /// way; others fail with `EINVAL`. Iteration stops at the first error returned by `f`, which is
+    /// then returned and the iterator is left unchanged. Once all segments have been visited, the
+    /// iterator is advanced past them.
+    pub fn for_each_segment<F: FnMut(&[u8]) -> Result>(&mut self, f: F) -> Result {
+        struct Context<F> {
+            f: F,
+            error: Option<Error>,
+        }
+
+        unsafe extern "C" fn segment_callback<F: FnMut(&[u8]) -> Result>(
+            vec: *mut bindings::kvec,
+            context: *mut c_types::c_void,
+        ) -> c_types::c_int {
+            // SAFETY: `context` is the `Context` passed to `iov_iter_for_each_range` below.
+            let context = &mut *(context as *mut Context<F>);
+            // The kernel keeps calling us after an error, so the remaining segments are skipped
+            // here instead.
+            if context.error.is_some() {
+                return 0;
+            }
+            // SAFETY: The kernel hands us a mapped segment that is valid for `iov_len` bytes.
+            let segment = core::slice::from_raw_parts((*vec).iov_base as *const u8, (*vec).iov_len);
+            match (context.f)(segment) {
+                Ok(()) => 0,
+                Err(e) => {
+                    let ret = e.to_kernel_errno();
+                    context.error = Some(e);
+                    ret
+                }
+            }
+        }
+
+        self.check_direction(bindings::WRITE)?;
+        let len = self.common_len();
+        let mut context = Context { f, error: None };
+        // SAFETY: `IovIter::ptr` is guaranteed to be valid by the type invariants, and `context`
+        // lives until the call returns.
+        let ret = unsafe {
+            bindings::iov_iter_for_each_range(
+                self.ptr,
+                len,
+                Some(segment_callback::<F>),
+                &mut context as *mut Context<F> as *mut c_types::c_void,
+            )
+        };
+        if let Some(e) = context.error {
+            return Err(e);
+        }
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        self.advance(len);
+        Ok(())
+    }
+
+    /// Moves the iterator back by `bytes`, undoing a previous copy or advance.
+    ///
+    /// This is useful to roll back a partially-consumed iterator when an operation fails. Only
+    /// bytes consumed through this wrapper can be reverted.
+    pub fn revert(&mut self, bytes: usize) {
+        let consumed = self.start_len - self.common_len();
+        debug_assert!(bytes <= consumed, "reverting more bytes than were consumed");
+        // SAFETY: `IovIter::ptr` is guaranteed to be valid by the type invariants, and we never
+        // revert past the point at which the wrapper was constructed.
+        unsafe { bindings::iov_iter_revert(self.ptr, bytes.min(consumed)) };
     }
 }
 
@@ -57,6 +173,7 @@ impl IoBufferWriter for IovIter {
     }
 
     fn clear(&mut self, mut len: usize) -> Result {
+        self.check_direction(bindings::READ)?;
         while len > 0 {
             // SAFETY: `IovIter::ptr` is guaranteed to be valid by the type invariants.
             let written = unsafe { bindings::iov_iter_zero(len, self.ptr) };
@@ -70,8 +187,17 @@ impl IoBufferWriter for IovIter {
     }
 
     unsafe fn write_raw(&mut self, data: *const u8, len: usize) -> Result {
+        self.check_direction(bindings::READ)?;
+        if len == 0 {
+            return Ok(());
+        }
+        if len > self.common_len() {
+            return Err(Error::EFAULT);
+        }
         let res = rust_helper_copy_to_iter(data as _, len, self.ptr);
         if res != len {
+            // Undo the partial copy so that the iterator is left as it was.
+            self.revert(res);
             Err(Error::EFAULT)
         } else {
             Ok(())
@@ -85,8 +211,17 @@ impl IoBufferReader for IovIter {
     }
 
     unsafe fn read_raw(&mut self, out: *mut u8, len: usize) -> Result {
+        self.check_direction(bindings::WRITE)?;
+        if len == 0 {
+            return Ok(());
+        }
+        if len > self.common_len() {
+            return Err(Error::EFAULT);
+        }
         let res = rust_helper_copy_from_iter(out as _, len, self.ptr);
         if res != len {
+            // Undo the partial copy so that the iterator is left as it was.
+            self.revert(res);
             Err(Error::EFAULT)
         } else {
             Ok(())
@@ -45,6 +45,7 @@ mod error;
 pub mod file;
 pub mod file_operations;
 pub mod miscdev;
+pub mod mm;
 pub mod pages;
 pub mod str;
 
@@ -77,6 +78,8 @@ pub use build_error::build_error;
 pub use crate::error::{Error, Result};
 pub use crate::types::Mode;
 
+use crate::str::CStr;
+
 /// Page size defined in terms of the `PAGE_SHIFT` macro from C.
 ///
 /// [`PAGE_SHIFT`]: ../../../include/asm-generic/page.h
@@ -96,24 +99,40 @@ pub trait KernelModule: Sized + Sync {
     ///
     /// Equivalent to the `module_init` macro in the C API.
     fn init() -> Result<Self>;
+
+    /// Called at module initialization time, with the module's name and [`ThisModule`].
+    ///
+    /// `name` is the name the module was declared with, so that, for example, devices can be
+    /// registered under it without repeating it. By default, this ignores its arguments and calls
+    /// [`KernelModule::init`].
+    fn init_with(_name: &'static CStr, _module: &'static ThisModule) -> Result<Self> {
+        Self::init()
+    }
 }
 
 /// Equivalent to `THIS_MODULE` in the C API.
 ///
 /// C header: `include/linux/export.h`
-pub struct ThisModule(*mut bindings::module);
+pub struct ThisModule(*mut bindings::module, &'static CStr);
 
 // SAFETY: `THIS_MODULE` may be used from all threads within a module.
 unsafe impl Sync for ThisModule {}
 
 impl ThisModule {
-    /// Creates a [`ThisModule`] given the `THIS_MODULE` pointer.
+    /// Creates a [`ThisModule`] given the `THIS_MODULE` pointer and the name of the module.
     ///
     /// # Safety
     ///
     /// The pointer must be equal to the right `THIS_MODULE`.
-    pub const unsafe fn from_ptr(ptr: *mut bindings::module) -> ThisModule {
-        ThisModule(ptr)
+    pub const unsafe fn from_ptr(ptr: *mut bindings::module, name: &'static CStr) -> ThisModule {
+        ThisModule(ptr, name)
+    }
+
+    /// Returns the name of the module.
+    ///
+    /// This is also available for built-in modules, for which `THIS_MODULE` is null.
+    pub fn name(&self) -> &'static CStr {
+        self.1
     }
 
     /// Locks the module parameters to access them.
@@ -7,10 +7,11 @@
 //! Reference: <https://www.kernel.org/doc/html/latest/driver-api/misc_devices.html>
 
 use crate::bindings;
+use crate::c_types;
 use crate::error::{Error, Result};
 use crate::file_operations::{FileOpenAdapter, FileOpener, FileOperationsVtable};
 use crate::str::CStr;
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 use core::marker::PhantomPinned;
 use core::pin::Pin;
 
@@ -18,6 +19,7 @@ use core::pin::Pin;
 pub struct Registration<T: Sync = ()> {
     registered: bool,
     mdev: bindings::miscdevice,
+    owned_name: Vec<u8>,
     _pin: PhantomPinned,
 
     /// Context initialised on construction and made available to all file instances on
@@ -33,6 +35,7 @@ impl<T: Sync> Registration<T> {
         Self {
             registered: false,
             mdev: bindings::miscdevice::default(),
+            owned_name: Vec::new(),
             _pin: PhantomPinned,
             context,
         }
@@ -51,6 +54,19 @@ impl<T: Sync> Registration<T> {
         Ok(r)
     }
 
+    /// Registers a miscellaneous device whose name is only known at runtime.
+    ///
+    /// Returns a pinned heap-allocated representation of the registration.
+    pub fn new_pinned_with_name<F: FileOpener<T>>(
+        name: &CStr,
+        minor: Option<i32>,
+        context: T,
+    ) -> Result<Pin<Box<Self>>> {
+        let mut r = Pin::from(Box::try_new(Self::new(context))?);
+        r.as_mut().register_with_name::<F>(name, minor)?;
+        Ok(r)
+    }
+
     /// Registers a miscellaneous device with the rest of the kernel.
     ///
     /// It must be pinned because the memory block that represents the registration is
@@ -67,18 +83,110 @@ impl<T: Sync> Registration<T> {
             return Err(Error::EINVAL);
         }
 
+        // SAFETY: `name` is a `&'static CStr`, so it outlives the registration.
+        unsafe { this.register_raw::<F>(name.as_char_ptr(), minor) }
+    }
+
+    /// Registers a miscellaneous device whose name is only known at runtime.
+    ///
+    /// Unlike [`Registration::register`], `name` need not be `'static`: a copy of it is kept in
+    /// the registration and used as the name of the device.
+    pub fn register_with_name<F: FileOpener<T>>(
+        self: Pin<&mut Self>,
+        name: &CStr,
+        minor: Option<i32>,
+    ) -> Result {
+        // SAFETY: We must ensure that we never move out of `this`.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.registered {
+            // Already registered.
+            return Err(Error::EINVAL);
+        }
+
+        let mut owned_name = Vec::new();
+        owned_name.try_reserve_exact(name.len_with_nul())?;
+        owned_name.extend_from_slice(name.as_bytes_with_nul());
+        this.owned_name = owned_name;
+
+        // SAFETY: The heap buffer of `owned_name` does not move with the registration, and it is
+        // only replaced or freed while the device is not registered.
+        unsafe { this.register_raw::<F>(this.owned_name.as_ptr() as _, minor) }
+    }
+
+    /// Registers the device with the given name.
+    ///
+    /// # Safety
+    ///
+    /// `name` must point to a null-terminated string that remains valid for as long as the device
+    /// is registered, and `self` must be pinned.
+    unsafe fn register_raw<F: FileOpener<T>>(
+        &mut self,
+        name: *const c_types::c_char,
+        minor: Option<i32>,
+    ) -> Result {
         // SAFETY: The adapter is compatible with `misc_register`.
-        this.mdev.fops = unsafe { FileOperationsVtable::<Self, F>::build() };
-        this.mdev.name = name.as_char_ptr();
-        this.mdev.minor = minor.unwrap_or(bindings::MISC_DYNAMIC_MINOR as i32);
+        self.mdev.fops = FileOperationsVtable::<Self, F>::build();
+        self.mdev.name = name;
+        self.mdev.minor = minor.unwrap_or(bindings::MISC_DYNAMIC_MINOR as i32);
 
-        let ret = unsafe { bindings::misc_register(&mut this.mdev) };
+        let ret = bindings::misc_register(&mut self.mdev);
         if ret < 0 {
             return Err(Error::from_kernel_errno(ret));
         }
-        this.registered = true;
+        self.registered = true;
         Ok(())
     }
+
+    /// Returns the name the device was registered with, or `None` if it is not registered.
+    pub fn name(&self) -> Option<&CStr> {
+        if !self.registered {
+            return None;
+        }
+
+        // SAFETY: `mdev.name` was set on registration to a string that outlives it.
+        Some(unsafe { CStr::from_char_ptr(self.mdev.name) })
+    }
+
+    /// Returns a mutable reference to the context.
+    ///
+    /// This allows the context to be updated after registration, for example, to change
+    /// configuration that is seen by files opened afterwards.
+    ///
+    /// # Safety
+    ///
+    /// While the returned reference is alive, the device must not be opened: [`FileOpener::open`]
+    /// reads the context without synchronisation. This holds, for example, if the device is not
+    /// registered.
+    pub unsafe fn context_mut(self: Pin<&mut Self>) -> &mut T {
+        // SAFETY: `context` is not structurally pinned, so it is fine to hand out a mutable
+        // reference to it.
+        &mut self.get_unchecked_mut().context
+    }
+
+    /// Returns the minor number of the device, or `None` if it is not registered.
+    ///
+    /// When registered with a dynamic minor, this is the one allocated by the kernel.
+    pub fn minor(&self) -> Option<i32> {
+        if !self.registered {
+            return None;
+        }
+        Some(self.mdev.minor)
+    }
+
+    /// Removes the miscellaneous device from the kernel.
+    ///
+    /// This allows the device to be torn down before the registration is dropped, for example,
+    /// when a later step of module initialisation fails. It does nothing if the device is not
+    /// registered, and dropping the registration afterwards does not deregister it again.
+    pub fn deregister(self: Pin<&mut Self>) {
+        // SAFETY: We must ensure that we never move out of `this`.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.registered {
+            // SAFETY: `mdev` was registered by `register` above and not deregistered since.
+            unsafe { bindings::misc_deregister(&mut this.mdev) };
+            this.registered = false;
+        }
+    }
 }
 
 impl<T: Sync> FileOpenAdapter for Registration<T> {
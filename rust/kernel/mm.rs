@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Memory management.
+//!
+//! C header: [`include/linux/mm.h`](../../../../include/linux/mm.h)
+
+use crate::bindings;
+
+/// Wraps the kernel's `struct vm_area_struct`.
+///
+/// # Invariants
+///
+/// The pointer [`VmArea::ptr`] is non-null and valid.
+pub struct VmArea {
+    pub(crate) ptr: *mut bindings::vm_area_struct,
+}
+
+impl VmArea {
+    /// Constructs a new [`struct vm_area_struct`] wrapper.
+    ///
+    /// # Safety
+    ///
+    /// The pointer `ptr` must be non-null and valid for the lifetime of the object.
+    pub(crate) unsafe fn from_ptr(ptr: *mut bindings::vm_area_struct) -> Self {
+        // INVARIANTS: the safety contract ensures the type invariant will hold.
+        Self { ptr }
+    }
+
+    /// Returns the first address of the area (`struct vm_area_struct::vm_start`).
+    pub fn start(&self) -> usize {
+        // SAFETY: `VmArea::ptr` is guaranteed to be valid by the type invariants.
+        unsafe { (*self.ptr).vm_start as _ }
+    }
+
+    /// Returns the first address after the end of the area (`struct vm_area_struct::vm_end`).
+    pub fn end(&self) -> usize {
+        // SAFETY: `VmArea::ptr` is guaranteed to be valid by the type invariants.
+        unsafe { (*self.ptr).vm_end as _ }
+    }
+
+    /// Returns the offset of the area within the file, in pages (`struct
+    /// vm_area_struct::vm_pgoff`).
+    pub fn pgoff(&self) -> usize {
+        // SAFETY: `VmArea::ptr` is guaranteed to be valid by the type invariants.
+        unsafe { (*self.ptr).vm_pgoff as _ }
+    }
+
+    /// Returns the flags of the area (`struct vm_area_struct::vm_flags`).
+    pub fn flags(&self) -> usize {
+        // SAFETY: `VmArea::ptr` is guaranteed to be valid by the type invariants.
+        unsafe { (*self.ptr).vm_flags as _ }
+    }
+}
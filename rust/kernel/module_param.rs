@@ -55,6 +55,15 @@ pub trait ModuleParam: core::fmt::Display + core::marker::Sized {
     /// `read` which will be generated by [`module::module`].
     fn value(&self) -> &Self::Value;
 
+    /// Check that a newly parsed value may be stored in the parameter.
+    ///
+    /// Called by `set_param` before replacing the current value; the error
+    /// is returned to the kernel if the check fails. All values are accepted
+    /// by default.
+    fn validate(&self) -> crate::Result {
+        Ok(())
+    }
+
     /// Set the module parameter from a string.
     ///
     /// Used to set the parameter value when loading the module or when set
@@ -75,6 +84,9 @@ pub trait ModuleParam: core::fmt::Display + core::marker::Sized {
         };
         match Self::try_from_param_arg(arg) {
             Some(new_value) => {
+                if let Err(e) = new_value.validate() {
+                    return e.to_kernel_errno();
+                }
                 let old_value = (*param).__bindgen_anon_1.arg as *mut Self;
                 let _ = core::ptr::replace(old_value, new_value);
                 0
@@ -5,8 +5,8 @@
 //! TODO: This module is a work in progress.
 
 use crate::{
-    bindings, c_types, io_buffer::IoBufferReader, user_ptr::UserSlicePtrReader, Error, Result,
-    PAGE_SIZE,
+    bindings, c_types, io_buffer::IoBufferReader, mm::VmArea, user_ptr::UserSlicePtrReader, Error,
+    Result, PAGE_SIZE,
 };
 use core::{marker::PhantomData, ptr};
 
@@ -57,14 +57,14 @@ impl<const ORDER: u32> Pages<ORDER> {
     /// Maps a single page at the given address in the given VM area.
     ///
     /// This is only meant to be used by pages of order 0.
-    pub fn insert_page(&self, vma: &mut bindings::vm_area_struct, address: usize) -> Result {
+    pub fn insert_page(&self, vma: &mut VmArea, address: usize) -> Result {
         if ORDER != 0 {
             return Err(Error::EINVAL);
         }
 
         // SAFETY: We check above that the allocation is of order 0. The range of `address` is
-        // already checked by `vm_insert_page`.
-        let ret = unsafe { bindings::vm_insert_page(vma, address as _, self.pages) };
+        // already checked by `vm_insert_page`. `vma.ptr` is valid by the `VmArea` invariants.
+        let ret = unsafe { bindings::vm_insert_page(vma.ptr, address as _, self.pages) };
         if ret != 0 {
             Err(Error::from_kernel_errno(ret))
         } else {
@@ -17,7 +17,9 @@ pub use super::build_assert;
 
 pub use module::{module, module_misc_device};
 
-pub use super::{pr_alert, pr_cont, pr_crit, pr_emerg, pr_err, pr_info, pr_notice, pr_warn};
+pub use super::{
+    pr_alert, pr_cont, pr_crit, pr_debug, pr_emerg, pr_err, pr_info, pr_notice, pr_warn,
+};
 
 pub use super::static_assert;
 
@@ -365,6 +365,35 @@ macro_rules! pr_info (
     )
 );
 
+/// Prints a debug-level message (level 7).
+///
+/// Use this level for debug messages.
+///
+/// Equivalent to the kernel's [`pr_debug`] macro, except that it does not
+/// support dynamic debug yet: messages are only printed if debug assertions
+/// are enabled (`CONFIG_RUST_DEBUG_ASSERTIONS`), and compiled out otherwise.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// [`alloc::format!`] for information about the formatting syntax.
+///
+/// [`pr_debug`]: https://www.kernel.org/doc/html/latest/core-api/printk-basics.html#c.pr_debug
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// pr_debug!("hello {}\n", "there");
+/// ```
+#[macro_export]
+#[doc(alias = "print")]
+macro_rules! pr_debug (
+    ($($arg:tt)*) => (
+        if cfg!(debug_assertions) {
+            $crate::print_macro!($crate::print::format_strings::DEBUG, false, $($arg)*)
+        }
+    )
+);
+
 /// Continues a previous log message in the same line.
 ///
 /// Use only when continuing a previous `pr_*!` macro (e.g. [`pr_info!`]).
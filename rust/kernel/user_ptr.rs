@@ -106,6 +106,24 @@ impl UserSlicePtr {
             UserSlicePtrWriter(self.0, self.1),
         )
     }
+
+    /// Splits the user slice into a [`UserSlicePtrReader`] for the first `mid` bytes and a
+    /// [`UserSlicePtrWriter`] for the rest.
+    ///
+    /// Unlike [`UserSlicePtr::reader_writer`], the two halves do not overlap, so data that is read
+    /// cannot be overwritten by mistake. Returns `EINVAL` if `mid` is past the end of the slice.
+    pub fn split_at(self, mid: usize) -> Result<(UserSlicePtrReader, UserSlicePtrWriter)> {
+        if mid > self.1 {
+            return Err(Error::EINVAL);
+        }
+        Ok((
+            UserSlicePtrReader(self.0, mid),
+            // Since this is not a pointer to a valid object in our program,
+            // we cannot use `add`, which has C-style rules for defined
+            // behavior.
+            UserSlicePtrWriter(self.0.wrapping_add(mid), self.1 - mid),
+        ))
+    }
 }
 
 /// A reader for [`UserSlicePtr`].
@@ -9,7 +9,7 @@
 #![deny(clippy::perf)]
 #![deny(clippy::style)]
 
-use proc_macro::{token_stream, Delimiter, Group, TokenStream, TokenTree};
+use proc_macro::{token_stream, Delimiter, Group, Spacing, TokenStream, TokenTree};
 
 fn try_ident(it: &mut token_stream::IntoIter) -> Option<String> {
     if let Some(TokenTree::Ident(ident)) = it.next() {
@@ -27,6 +27,17 @@ fn try_literal(it: &mut token_stream::IntoIter) -> Option<String> {
     }
 }
 
+fn try_numeric_literal(it: &mut token_stream::IntoIter) -> Option<String> {
+    // Negative literals are lexed as a `-` followed by the literal.
+    let sign = match it.clone().next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '-' => "-",
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '+' => "",
+        _ => return try_literal(it),
+    };
+    it.next();
+    try_literal(it).map(|literal| format!("{}{}", sign, literal))
+}
+
 fn try_byte_string(it: &mut token_stream::IntoIter) -> Option<String> {
     try_literal(it).and_then(|byte_string| {
         if byte_string.starts_with("b\"") && byte_string.ends_with('\"') {
@@ -69,6 +80,7 @@ fn expect_byte_string(it: &mut token_stream::IntoIter) -> String {
 enum ParamType {
     Ident(String),
     Array { vals: String, max_length: usize },
+    Custom { ops: String, value_type: String },
 }
 
 fn expect_array_fields(it: &mut token_stream::IntoIter) -> ParamType {
@@ -76,13 +88,29 @@ fn expect_array_fields(it: &mut token_stream::IntoIter) -> ParamType {
     let vals = expect_ident(it);
     assert_eq!(expect_punct(it), ',');
     let max_length_str = expect_literal(it);
-    let max_length = max_length_str
-        .parse::<usize>()
-        .expect("Expected usize length");
+    let (radix, digits) = split_radix(&max_length_str);
+    let max_length = match usize::from_str_radix(digits, radix) {
+        Ok(0) => panic!("`ArrayParam` length must be greater than zero"),
+        Ok(max_length) => max_length,
+        Err(_) => panic!("Invalid `ArrayParam` length \"{}\"", max_length_str),
+    };
     assert_eq!(expect_punct(it), '>');
     ParamType::Array { vals, max_length }
 }
 
+fn expect_custom_fields(it: &mut token_stream::IntoIter) -> ParamType {
+    let group = expect_group(it);
+    assert_eq!(group.delimiter(), Delimiter::Parenthesis);
+    let mut it = group.stream().into_iter();
+    let ops = expect_tokens_until(&mut it, ',');
+    assert_eq!(expect_punct(&mut it), ',');
+    let value_type = it.collect::<TokenStream>().to_string();
+    if value_type.is_empty() {
+        panic!("Expected value type of custom param");
+    }
+    ParamType::Custom { ops, value_type }
+}
+
 fn expect_type(it: &mut token_stream::IntoIter) -> ParamType {
     if let TokenTree::Ident(ident) = it
         .next()
@@ -90,6 +118,7 @@ fn expect_type(it: &mut token_stream::IntoIter) -> ParamType {
     {
         match ident.to_string().as_ref() {
             "ArrayParam" => expect_array_fields(it),
+            "custom" => expect_custom_fields(it),
             _ => ParamType::Ident(ident.to_string()),
         }
     } else {
@@ -97,18 +126,71 @@ fn expect_type(it: &mut token_stream::IntoIter) -> ParamType {
     }
 }
 
+/// Returns the character of `token` if it is a `Punct` joined to the next one, e.g. the `-` in `->`.
+fn joint_punct(token: Option<&TokenTree>) -> Option<char> {
+    match token {
+        Some(TokenTree::Punct(punct)) if punct.spacing() == Spacing::Joint => Some(punct.as_char()),
+        _ => None,
+    }
+}
+
+/// Collects tokens until `end`, which is not looked for inside of angle brackets so that, e.g.,
+/// the comma in `Foo<A, B>` is skipped.
+fn expect_tokens_until(it: &mut token_stream::IntoIter, end: char) -> String {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    loop {
+        let mut lookahead = it.clone();
+        let token = match lookahead.next() {
+            Some(token) => token,
+            None => panic!("Expected '{}'", end),
+        };
+        if let TokenTree::Punct(ref punct) = token {
+            let prev = joint_punct(tokens.last());
+            let next = match lookahead.next() {
+                Some(TokenTree::Punct(next)) if punct.spacing() == Spacing::Joint => {
+                    Some(next.as_char())
+                }
+                _ => None,
+            };
+            match punct.as_char() {
+                c if c == end && depth == 0 => break,
+                // Not `<<` or `<=`.
+                '<' if prev != Some('<') && next != Some('<') && next != Some('=') => depth += 1,
+                // Not `->` or `=>`.
+                '>' if depth > 0 && prev != Some('-') && prev != Some('=') => depth -= 1,
+                _ => (),
+            }
+        }
+        tokens.push(token);
+        it.next();
+    }
+    if tokens.is_empty() {
+        panic!("Expected tokens before '{}'", end);
+    }
+    tokens.into_iter().collect::<TokenStream>().to_string()
+}
+
 fn expect_end(it: &mut token_stream::IntoIter) {
     if it.next().is_some() {
         panic!("Expected end");
     }
 }
 
-fn get_literal(it: &mut token_stream::IntoIter, expected_name: &str) -> String {
+fn get_optional_bool(it: &mut token_stream::IntoIter, expected_name: &str) -> bool {
+    match it.clone().next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == expected_name => (),
+        _ => return false,
+    }
     assert_eq!(expect_ident(it), expected_name);
     assert_eq!(expect_punct(it), ':');
-    let literal = expect_literal(it);
+    let value = match expect_ident(it).as_ref() {
+        "true" => true,
+        "false" => false,
+        _ => panic!("Expected `true` or `false` for \"{}\"", expected_name),
+    };
     assert_eq!(expect_punct(it), ',');
-    literal
+    value
 }
 
 fn get_byte_string(it: &mut token_stream::IntoIter, expected_name: &str) -> String {
@@ -119,6 +201,50 @@ fn get_byte_string(it: &mut token_stream::IntoIter, expected_name: &str) -> Stri
     byte_string
 }
 
+/// Decodes the escape sequences of the contents of a byte string literal.
+fn unescape_byte_string(s: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            bytes.push(c as u8);
+            continue;
+        }
+        match chars.next().expect("Unterminated escape sequence") {
+            'n' => bytes.push(b'\n'),
+            'r' => bytes.push(b'\r'),
+            't' => bytes.push(b'\t'),
+            '0' => bytes.push(b'\0'),
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                bytes.push(u8::from_str_radix(&hex, 16).expect("Invalid \\x escape"));
+            }
+            // Line continuation: skip the newline and the leading whitespace of the next line.
+            '\n' => {
+                while chars.peek().map_or(false, |c| c.is_whitespace()) {
+                    chars.next();
+                }
+            }
+            c => bytes.push(c as u8),
+        }
+    }
+    bytes
+}
+
+/// Escapes `bytes` so that they can be placed inside a byte string literal.
+fn escape_byte_string(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    for &b in bytes {
+        match b {
+            b'\\' => s.push_str("\\\\"),
+            b'"' => s.push_str("\\\""),
+            b' '..=b'~' => s.push(b as char),
+            _ => s.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    s
+}
+
 fn __build_modinfo_string_base(
     module: &str,
     field: &str,
@@ -138,6 +264,9 @@ fn __build_modinfo_string_base(
         // Loadable modules' modinfo strings go as-is.
         format!("{field}={content}", field = field, content = content)
     };
+    // The content comes from byte string literals, so it may contain escape sequences. Decode
+    // them to get the real length, then re-escape so that the literal below is always valid.
+    let bytes = unescape_byte_string(&string);
 
     format!(
         "
@@ -152,8 +281,8 @@ fn __build_modinfo_string_base(
             "#[cfg(MODULE)]"
         },
         variable = variable,
-        length = string.len() + 1,
-        string = string,
+        length = bytes.len() + 1,
+        string = escape_byte_string(&bytes),
     )
 }
 
@@ -194,7 +323,13 @@ fn build_modinfo_string_optional(module: &str, field: &str, content: Option<&str
     }
 }
 
-fn build_modinfo_string_param(module: &str, field: &str, param: &str, content: &str) -> String {
+fn build_modinfo_string_param(
+    module: &str,
+    field: &str,
+    param: &str,
+    content: &str,
+    cfg: &str,
+) -> String {
     let variable = format!(
         "__{module}_{field}_{param}",
         module = module,
@@ -202,26 +337,133 @@ fn build_modinfo_string_param(module: &str, field: &str, param: &str, content: &
         param = param
     );
     let content = format!("{param}:{content}", param = param, content = content);
-    __build_modinfo_string_base(module, field, &content, &variable, true)
-        + &__build_modinfo_string_base(module, field, &content, &variable, false)
+    // `cfg` is an extra attribute (or nothing) placed in front of both generated statics.
+    format!(
+        "{cfg}{builtin}{cfg}{loadable}",
+        cfg = cfg,
+        builtin = __build_modinfo_string_base(module, field, &content, &variable, true),
+        loadable = __build_modinfo_string_base(module, field, &content, &variable, false),
+    )
 }
 
-fn permissions_are_readonly(perms: &str) -> bool {
-    let (radix, digits) = if let Some(n) = perms.strip_prefix("0x") {
+fn split_radix(literal: &str) -> (u32, &str) {
+    if let Some(n) = literal.strip_prefix("0x") {
         (16, n)
-    } else if let Some(n) = perms.strip_prefix("0o") {
+    } else if let Some(n) = literal.strip_prefix("0o") {
         (8, n)
-    } else if let Some(n) = perms.strip_prefix("0b") {
+    } else if let Some(n) = literal.strip_prefix("0b") {
         (2, n)
     } else {
-        (10, perms)
-    };
+        (10, literal)
+    }
+}
+
+fn parse_permissions(perms: &str) -> u32 {
+    let (radix, digits) = split_radix(perms);
     match u32::from_str_radix(digits, radix) {
-        Ok(perms) => perms & 0o222 == 0,
-        Err(_) => false,
+        Ok(perms) => perms,
+        Err(_) => panic!("Invalid permissions \"{}\"", perms),
     }
 }
 
+fn permissions_are_readonly(perms: &str) -> bool {
+    parse_permissions(perms) & 0o222 == 0
+}
+
+/// Parses the `aliases` key of a parameter, i.e. a list of byte strings with its other names.
+fn get_aliases(it: &mut token_stream::IntoIter, param_name: &str) -> Vec<String> {
+    assert_eq!(expect_ident(it), "aliases");
+    assert_eq!(expect_punct(it), ':');
+    let group = expect_group(it);
+    assert_eq!(group.delimiter(), Delimiter::Bracket);
+    assert_eq!(expect_punct(it), ',');
+
+    let mut aliases = Vec::new();
+    let mut group_it = group.stream().into_iter();
+    while group_it.clone().next().is_some() {
+        let alias = expect_byte_string(&mut group_it);
+        // Aliases end up in symbol names, so they must be valid identifiers.
+        if alias.is_empty()
+            || alias.starts_with(|c: char| c.is_ascii_digit())
+            || !alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            panic!(
+                "Invalid alias \"{}\" of parameter \"{}\". Only ASCII letters, digits and underscores are allowed.",
+                alias, param_name
+            );
+        }
+        if alias == param_name || aliases.contains(&alias) {
+            panic!(
+                "Alias \"{}\" of parameter \"{}\" is used more than once.",
+                alias, param_name
+            );
+        }
+        aliases.push(alias);
+        match group_it.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => (),
+            None => break,
+            _ => panic!("Expected ',' or end of aliases"),
+        }
+    }
+    if aliases.is_empty() {
+        panic!(
+            "Expected at least one alias for parameter \"{}\"",
+            param_name
+        );
+    }
+    aliases
+}
+
+/// Parses the `permissions` key of a parameter.
+///
+/// Returns the expression to use for the `perm` field, whether the parameter is read only, and the
+/// conditions to check at compile time for permissions that are not known to the macro (e.g. a
+/// `const`).
+fn get_permissions(
+    it: &mut token_stream::IntoIter,
+    param_name: &str,
+) -> (String, bool, Vec<String>) {
+    assert_eq!(expect_ident(it), "permissions");
+    assert_eq!(expect_punct(it), ':');
+    if let Some(TokenTree::Literal(_)) = it.clone().next() {
+        let permissions = expect_literal(it);
+        assert_eq!(expect_punct(it), ',');
+        if parse_permissions(&permissions) > 0o7777 {
+            panic!(
+                "Permissions \"{}\" of parameter \"{}\" are out of range (maximum is 0o7777).",
+                permissions, param_name
+            );
+        }
+        if let Some(TokenTree::Ident(ident)) = it.clone().next() {
+            if ident.to_string() == "readonly" {
+                panic!(
+                    "Key \"readonly\" of parameter \"{}\" is only allowed if `permissions` is not a literal.",
+                    param_name
+                );
+            }
+        }
+        let readonly = permissions_are_readonly(&permissions);
+        return (permissions, readonly, Vec::new());
+    }
+
+    // Anything else is passed through verbatim, so it can only be checked by the compiler. Such
+    // permissions are assumed to be writable unless stated otherwise.
+    let permissions = expect_tokens_until(it, ',');
+    assert_eq!(expect_punct(it), ',');
+    let readonly = get_optional_bool(it, "readonly");
+    // The value is widened first so that negative or over-wide values cannot wrap into range.
+    let value = format!("(({}) as i128)", permissions);
+    let mut checks = vec![format!("{value} >= 0 && {value} <= 0o7777", value = value)];
+    if readonly {
+        checks.push(format!("{} & 0o222 == 0", value));
+    }
+    (
+        format!("({}) as kernel::bindings::umode_t", permissions),
+        readonly,
+        checks,
+    )
+}
+
 fn param_ops_path(param_type: &str) -> &'static str {
     match param_type {
         "bool" => "kernel::module_param::PARAM_OPS_BOOL",
@@ -249,17 +491,44 @@ fn try_simple_param_val(
             try_byte_string(param_it)
                 .map(|s| format!("kernel::module_param::StringParam::Ref(b\"{}\")", s))
         }),
-        _ => Box::new(|param_it| try_literal(param_it)),
+        _ => Box::new(|param_it| try_numeric_literal(param_it)),
+    }
+}
+
+fn zero_param_val(param_type: &ParamType) -> String {
+    match param_type {
+        ParamType::Ident(ref param_type) => match param_type.as_ref() {
+            "bool" => "false".to_string(),
+            "str" => "kernel::module_param::StringParam::Ref(b\"\")".to_string(),
+            _ => "0".to_string(),
+        },
+        ParamType::Array { .. } => "kernel::module_param::ArrayParam::create(&[])".to_string(),
+        ParamType::Custom { .. } => panic!("Custom params require a default value"),
     }
 }
 
 fn get_default(param_type: &ParamType, param_it: &mut token_stream::IntoIter) -> String {
+    // The `default` key is optional; if missing, the zero value of the type is used.
+    match param_it.clone().next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "default" => (),
+        _ => return zero_param_val(param_type),
+    }
+
+    if let ParamType::Custom { .. } = param_type {
+        assert_eq!(expect_ident(param_it), "default");
+        assert_eq!(expect_punct(param_it), ':');
+        let default = expect_tokens_until(param_it, ',');
+        assert_eq!(expect_punct(param_it), ',');
+        return default;
+    }
+
     let try_param_val = match param_type {
         ParamType::Ident(ref param_type)
         | ParamType::Array {
             vals: ref param_type,
             max_length: _,
         } => try_simple_param_val(param_type),
+        ParamType::Custom { .. } => unreachable!(),
     };
     assert_eq!(expect_ident(param_it), "default");
     assert_eq!(expect_punct(param_it), ':');
@@ -267,7 +536,7 @@ fn get_default(param_type: &ParamType, param_it: &mut token_stream::IntoIter) ->
         ParamType::Ident(_) => try_param_val(param_it).expect("Expected default param value"),
         ParamType::Array {
             vals: _,
-            max_length: _,
+            max_length,
         } => {
             let group = expect_group(param_it);
             assert_eq!(group.delimiter(), Delimiter::Bracket);
@@ -277,11 +546,25 @@ fn get_default(param_type: &ParamType, param_it: &mut token_stream::IntoIter) ->
             while let Some(default_val) = try_param_val(&mut it) {
                 default_vals.push(default_val);
                 match it.next() {
+                    Some(TokenTree::Punct(punct)) if punct.as_char() == ';' => {
+                        // Repeat expression, e.g. `[0; 4]`.
+                        assert_eq!(default_vals.len(), 1, "Expected a single repeated value");
+                        let count = expect_literal(&mut it)
+                            .parse::<usize>()
+                            .expect("Invalid array default repeat count");
+                        expect_end(&mut it);
+                        default_vals = vec![default_vals.remove(0); count];
+                        break;
+                    }
                     Some(TokenTree::Punct(punct)) => assert_eq!(punct.as_char(), ','),
                     None => break,
-                    _ => panic!("Expected ',' or end of array default values"),
+                    _ => panic!("Expected ',', ';' or end of array default values"),
                 }
             }
+            assert!(
+                default_vals.len() <= *max_length,
+                "Too many array default values"
+            );
 
             let mut default_array = "kernel::module_param::ArrayParam::create(&[".to_string();
             default_array.push_str(
@@ -294,11 +577,19 @@ fn get_default(param_type: &ParamType, param_it: &mut token_stream::IntoIter) ->
             default_array.push_str("])");
             default_array
         }
+        ParamType::Custom { .. } => unreachable!(),
     };
     assert_eq!(expect_punct(param_it), ',');
     default
 }
 
+/// Computes the 64-bit FNV-1a hash of `bytes`, which is stable across compiler versions.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
+
 fn generated_array_ops_name(vals: &str, max_length: usize) -> String {
     format!(
         "__generated_array_ops_{vals}_{max_length}",
@@ -316,6 +607,8 @@ struct ModuleInfo {
     description: Option<String>,
     alias: Option<String>,
     params: Option<Group>,
+    minor: Option<String>,
+    context: Option<(String, String)>,
 }
 
 impl ModuleInfo {
@@ -331,6 +624,8 @@ impl ModuleInfo {
             "alias",
             "alias_rtnl_link",
             "params",
+            "minor",
+            "context",
         ];
         const REQUIRED_KEYS: &[&str] = &["type", "name", "license"];
         let mut seen_keys = Vec::new();
@@ -352,7 +647,7 @@ impl ModuleInfo {
             assert_eq!(expect_punct(it), ':');
 
             match key.as_str() {
-                "type" => info.type_ = expect_ident(it),
+                "type" => info.type_ = expect_tokens_until(it, ','),
                 "name" => info.name = expect_byte_string(it),
                 "author" => info.author = Some(expect_byte_string(it)),
                 "description" => info.description = Some(expect_byte_string(it)),
@@ -362,6 +657,12 @@ impl ModuleInfo {
                     info.alias = Some(format!("rtnl-link-{}", expect_byte_string(it)))
                 }
                 "params" => info.params = Some(expect_group(it)),
+                "minor" => info.minor = Some(expect_literal(it)),
+                "context" => {
+                    let type_ = expect_tokens_until(it, '=');
+                    assert_eq!(expect_punct(it), '=');
+                    info.context = Some((type_, expect_tokens_until(it, ',')));
+                }
                 _ => panic!(
                     "Unknown key \"{}\". Valid keys are: {:?}.",
                     key, EXPECTED_KEYS
@@ -395,6 +696,20 @@ impl ModuleInfo {
             );
         }
 
+        // The name is used for identifiers, `__LOG_PREFIX` and the modinfo strings, so it must be
+        // a valid module name.
+        if info.name.is_empty()
+            || !info
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            panic!(
+                "Invalid module name \"{}\". Only ASCII letters, digits and underscores are allowed.",
+                info.name
+            );
+        }
+
         info
     }
 }
@@ -450,7 +765,7 @@ impl ModuleInfo {
 /// ```
 ///
 /// # Supported argument types
-///   - `type`: type which implements the [`KernelModule`] trait (required).
+///   - `type`: type (or path to a type) which implements the [`KernelModule`] trait (required).
 ///   - `name`: byte array of the name of the kernel module (required).
 ///   - `author`: byte array of the author of the kernel module.
 ///   - `description`: byte array of the description of the kernel module.
@@ -475,6 +790,48 @@ impl ModuleInfo {
 ///   - `str`: Corresponds to C `charp` param type. Reading returns a byte slice.
 ///   - `ArrayParam<T,N>`: Corresponds to C parameters created using `module_param_array`. An array
 ///     of `T`'s of length at **most** `N`.
+///   - `custom(OPS, T)`: A parameter of type `T`, which must implement `ModuleParam`, using the
+///     `kernel_param_ops` static at path `OPS` (e.g. one generated by `make_param_ops!`). A
+///     `default` expression is required.
+///
+/// The `default` of an `ArrayParam` may be given either as a list (e.g. `[1, 2, 3]`) or as a repeat
+/// expression (e.g. `[0; 4]`), and may not hold more than `N` values.
+///
+/// A parameter may also be declared in a short form, giving only its type and default value (e.g.
+/// `foo: u32 = 42,`). This is equivalent to using `permissions: 0` and an empty `description`.
+///
+/// The `default` key of a parameter may be omitted, in which case the parameter defaults to `0`,
+/// `false`, an empty string, or an empty array, depending on its type.
+///
+/// A parameter may start with a `cfg` key naming a configuration option (e.g. `cfg: CONFIG_FOO,`),
+/// in which case the parameter only exists if that option is enabled.
+///
+/// The `permissions` key of a parameter may be replaced by `hidden: true`, in which case the
+/// parameter can only be set at load time (or on the kernel command line) and is not exposed in
+/// `/sys/module/<name>/parameters`. This is the same as `permissions: 0`.
+///
+/// The `permissions` of a parameter may also be given as a constant (e.g. `permissions: PERMS,`).
+/// Since the macro cannot know its value, the parameter is then treated as writable, unless
+/// `readonly: true,` follows `permissions`. Both the range and read-only-ness are checked at compile
+/// time.
+///
+/// A writable `str` parameter can be replaced while it is being read, so it must acknowledge this
+/// with `racy_ok: true,` right after `permissions`, and be read through the locked accessor.
+///
+/// An integer parameter may have a `range` key right before `description` (e.g. `range: 1..=100,`).
+/// Setting the parameter to a value outside of the range then fails with `ERANGE`. The default
+/// value is not checked.
+///
+/// A parameter may have an `aliases` key right after `description` listing other names it also
+/// responds to (e.g. `aliases: [b"old_name"],`), which is useful to keep old names working. In C,
+/// this is done with an extra `module_param_named`. Each alias refers to the same value.
+///
+/// A parameter may also end with a `parmtype` key holding a byte string, which overrides the type
+/// advertised in the `parmtype` modinfo entry (e.g. `parmtype: b"array of int",`).
+///
+/// When built with `--cfg rust_param_schema`, the module also gets a `__PARAM_SCHEMA` constant
+/// listing the name, type and permissions of each parameter, so that tools can find them without
+/// parsing the modinfo strings. This is not meant for regular kernel builds.
 ///
 /// `invbool` is unsupported: it was only ever used in a few modules.
 /// Consider using a `bool` and inverting the logic instead.
@@ -484,10 +841,17 @@ pub fn module(ts: TokenStream) -> TokenStream {
 
     let info = ModuleInfo::parse(&mut it);
 
+    if info.minor.is_some() || info.context.is_some() {
+        panic!("Keys \"minor\" and \"context\" are only supported by `module_misc_device!`.");
+    }
+
     let name = info.name.clone();
 
     let mut array_types_to_generate = Vec::new();
     let mut params_modinfo = String::new();
+    let mut params_schema = String::new();
+    // Names of the parameters and their aliases, which share the same namespace.
+    let mut param_names: Vec<String> = Vec::new();
     if let Some(params) = info.params {
         assert_eq!(params.delimiter(), Delimiter::Brace);
 
@@ -502,15 +866,100 @@ pub fn module(ts: TokenStream) -> TokenStream {
 
             assert_eq!(expect_punct(&mut it), ':');
             let param_type = expect_type(&mut it);
-            let group = expect_group(&mut it);
+            let param_stream = match it.clone().next() {
+                // Short form, e.g. `foo: u32 = 42,`, which is expanded to the long one.
+                Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => {
+                    it.next();
+                    let default = expect_tokens_until(&mut it, ',');
+                    format!("default: {}, permissions: 0, description: b\"\",", default)
+                        .parse::<TokenStream>()
+                        .unwrap()
+                }
+                _ => {
+                    let group = expect_group(&mut it);
+                    assert_eq!(group.delimiter(), Delimiter::Brace);
+                    group.stream()
+                }
+            };
             assert_eq!(expect_punct(&mut it), ',');
 
-            assert_eq!(group.delimiter(), Delimiter::Brace);
-
-            let mut param_it = group.stream().into_iter();
+            let mut param_it = param_stream.into_iter();
+            let param_cfg = match param_it.clone().next() {
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "cfg" => {
+                    assert_eq!(expect_ident(&mut param_it), "cfg");
+                    assert_eq!(expect_punct(&mut param_it), ':');
+                    let cfg = format!("#[cfg({})]", expect_ident(&mut param_it));
+                    assert_eq!(expect_punct(&mut param_it), ',');
+                    cfg
+                }
+                _ => "".to_string(),
+            };
             let param_default = get_default(&param_type, &mut param_it);
-            let param_permissions = get_literal(&mut param_it, "permissions");
+            let param_hidden = get_optional_bool(&mut param_it, "hidden");
+            // Hidden parameters get no sysfs entry, which the kernel expresses with `perm == 0`.
+            let (param_permissions, param_readonly, param_permissions_checks) = if param_hidden {
+                if let Some(TokenTree::Ident(ident)) = param_it.clone().next() {
+                    if ident.to_string() == "permissions" {
+                        panic!(
+                            "Parameter \"{}\" has both `hidden` and `permissions`. `hidden: true` replaces `permissions`.",
+                            param_name
+                        );
+                    }
+                }
+                ("0".to_string(), true, Vec::new())
+            } else {
+                get_permissions(&mut param_it, &param_name)
+            };
+            let param_racy_ok = get_optional_bool(&mut param_it, "racy_ok");
+            if param_type == ParamType::Ident("str".to_string())
+                && !param_readonly
+                && !param_racy_ok
+            {
+                panic!(
+                    "Writable `str` parameter \"{}\" can change while it is being read. Add \
+                     `racy_ok: true,` after `permissions` to acknowledge this.",
+                    param_name
+                );
+            }
+            let param_range = match param_it.clone().next() {
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "range" => {
+                    assert_eq!(expect_ident(&mut param_it), "range");
+                    assert_eq!(expect_punct(&mut param_it), ':');
+                    let range = expect_tokens_until(&mut param_it, ',');
+                    assert_eq!(expect_punct(&mut param_it), ',');
+                    Some(range)
+                }
+                _ => None,
+            };
             let param_description = get_byte_string(&mut param_it, "description");
+            let param_aliases = match param_it.clone().next() {
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "aliases" => {
+                    get_aliases(&mut param_it, &param_name)
+                }
+                _ => Vec::new(),
+            };
+            if param_names.contains(&param_name) {
+                panic!(
+                    "Parameter \"{}\" collides with another parameter or alias.",
+                    param_name
+                );
+            }
+            param_names.push(param_name.clone());
+            for alias in &param_aliases {
+                if param_names.contains(alias) {
+                    panic!(
+                        "Alias \"{}\" of parameter \"{}\" collides with another parameter or alias.",
+                        alias, param_name
+                    );
+                }
+                param_names.push(alias.clone());
+            }
+            let param_parmtype = match param_it.clone().next() {
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "parmtype" => {
+                    Some(get_byte_string(&mut param_it, "parmtype"))
+                }
+                _ => None,
+            };
             expect_end(&mut param_it);
 
             // TODO: more primitive types
@@ -530,20 +979,49 @@ pub fn module(ts: TokenStream) -> TokenStream {
                         generated_array_ops_name(vals, max_length),
                     )
                 }
+                ParamType::Custom {
+                    ref ops,
+                    ref value_type,
+                } => (value_type.replace(' ', ""), ops.clone()),
             };
 
             params_modinfo.push_str(&build_modinfo_string_param(
                 &name,
                 "parmtype",
                 &param_name,
-                &param_kernel_type,
+                param_parmtype.as_deref().unwrap_or(&param_kernel_type),
+                &param_cfg,
             ));
             params_modinfo.push_str(&build_modinfo_string_param(
                 &name,
                 "parm",
                 &param_name,
                 &param_description,
+                &param_cfg,
+            ));
+            params_schema.push_str(&format!(
+                "{}(\"{}\", \"{}\", ({}) as u32),",
+                param_cfg,
+                param_name,
+                param_parmtype.as_deref().unwrap_or(&param_kernel_type),
+                param_permissions,
             ));
+            for alias in &param_aliases {
+                params_modinfo.push_str(&build_modinfo_string_param(
+                    &name,
+                    "parmtype",
+                    alias,
+                    param_parmtype.as_deref().unwrap_or(&param_kernel_type),
+                    &param_cfg,
+                ));
+                params_modinfo.push_str(&build_modinfo_string_param(
+                    &name,
+                    "parm",
+                    alias,
+                    &param_description,
+                    &param_cfg,
+                ));
+            }
             let param_type_internal = match param_type {
                 ParamType::Ident(ref param_type) => match param_type.as_ref() {
                     "str" => "kernel::module_param::StringParam".to_string(),
@@ -557,8 +1035,71 @@ pub fn module(ts: TokenStream) -> TokenStream {
                     vals = vals,
                     max_length = max_length
                 ),
+                ParamType::Custom { ref value_type, .. } => value_type.clone(),
+            };
+
+            // A range is enforced by wrapping the value in a type whose `validate` checks it, with
+            // its own `kernel_param_ops`.
+            let (param_type_internal, ops, param_default) = match param_range {
+                None => (param_type_internal, ops, param_default),
+                Some(range) => {
+                    match param_type {
+                        ParamType::Ident(ref t) if !matches!(t.as_ref(), "bool" | "str") => (),
+                        _ => panic!("Key \"range\" is only supported by integer parameters."),
+                    }
+                    let range_type = format!("__{}_{}_RangeParam", name, param_name);
+                    let range_ops = format!("__{}_{}_range_ops", name, param_name);
+                    params_modinfo.push_str(&format!(
+                        "
+                            {cfg}
+                            #[repr(transparent)]
+                            struct {range_type}({param_type});
+
+                            {cfg}
+                            impl core::fmt::Display for {range_type} {{
+                                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {{
+                                    core::fmt::Display::fmt(&self.0, f)
+                                }}
+                            }}
+
+                            {cfg}
+                            impl kernel::module_param::ModuleParam for {range_type} {{
+                                type Value = {param_type};
+
+                                const NOARG_ALLOWED: bool = false;
+
+                                fn try_from_param_arg(arg: Option<&'static [u8]>) -> Option<Self> {{
+                                    <{param_type} as kernel::module_param::ModuleParam>::try_from_param_arg(arg).map(Self)
+                                }}
+
+                                fn value(&self) -> &Self::Value {{
+                                    &self.0
+                                }}
+
+                                fn validate(&self) -> kernel::Result {{
+                                    if ({range}).contains(&self.0) {{
+                                        Ok(())
+                                    }} else {{
+                                        Err(kernel::Error::ERANGE)
+                                    }}
+                                }}
+                            }}
+
+                            {cfg}
+                            kernel::make_param_ops!({range_ops}, {range_type});
+                        ",
+                        cfg = param_cfg,
+                        range_type = range_type,
+                        range_ops = range_ops,
+                        param_type = param_type_internal,
+                        range = range,
+                    ));
+                    let param_default = format!("{}({})", range_type, param_default);
+                    (range_type, range_ops, param_default)
+                }
             };
-            let read_func = if permissions_are_readonly(&param_permissions) {
+
+            let read_func = if param_readonly {
                 format!(
                     "
                         fn read(&self) -> &<{param_type_internal} as kernel::module_param::ModuleParam>::Value {{
@@ -595,12 +1136,16 @@ pub fn module(ts: TokenStream) -> TokenStream {
             params_modinfo.push_str(
                 &format!(
                     "
+                    {cfg}
                     static mut __{name}_{param_name}_value: {param_type_internal} = {param_default};
 
+                    {cfg}
                     struct __{name}_{param_name};
 
+                    {cfg}
                     impl __{name}_{param_name} {{ {read_func} }}
 
+                    {cfg}
                     const {param_name}: __{name}_{param_name} = __{name}_{param_name};
 
                     // Note: the C macro that generates the static structs for the `__param` section
@@ -610,18 +1155,23 @@ pub fn module(ts: TokenStream) -> TokenStream {
                     // not the case anymore, so we simplify to a transparent representation here
                     // in the expectation that it is not needed anymore.
                     // TODO: revisit this to confirm the above comment and remove it if it happened
+                    {cfg}
                     #[repr(transparent)]
                     struct __{name}_{param_name}_RacyKernelParam(kernel::bindings::kernel_param);
 
+                    {cfg}
                     unsafe impl Sync for __{name}_{param_name}_RacyKernelParam {{
                     }}
 
+                    {cfg}
                     #[cfg(not(MODULE))]
                     const __{name}_{param_name}_name: *const kernel::c_types::c_char = b\"{name}.{param_name}\\0\" as *const _ as *const kernel::c_types::c_char;
 
+                    {cfg}
                     #[cfg(MODULE)]
                     const __{name}_{param_name}_name: *const kernel::c_types::c_char = b\"{param_name}\\0\" as *const _ as *const kernel::c_types::c_char;
 
+                    {cfg}
                     #[link_section = \"__param\"]
                     #[used]
                     static __{name}_{param_name}_struct: __{name}_{param_name}_RacyKernelParam = __{name}_{param_name}_RacyKernelParam(kernel::bindings::kernel_param {{
@@ -637,6 +1187,8 @@ pub fn module(ts: TokenStream) -> TokenStream {
                         flags: 0,
                         __bindgen_anon_1: {kparam}
                     }});
+
+                    {permissions_checks}
                     ",
                     name = name,
                     param_type_internal = param_type_internal,
@@ -645,9 +1197,53 @@ pub fn module(ts: TokenStream) -> TokenStream {
                     param_name = param_name,
                     ops = ops,
                     permissions = param_permissions,
+                    permissions_checks = param_permissions_checks
+                        .iter()
+                        .map(|check| format!("{}kernel::static_assert!({});", param_cfg, check))
+                        .collect::<String>(),
                     kparam = kparam,
+                    cfg = param_cfg,
                 )
             );
+
+            // Each alias gets its own `kernel_param`, pointing to the same value and ops.
+            for alias in &param_aliases {
+                params_modinfo.push_str(&format!(
+                    "
+                        {cfg}
+                        #[cfg(not(MODULE))]
+                        const __{name}_{param_name}_{alias}_alias_name: *const kernel::c_types::c_char = b\"{name}.{alias}\\0\" as *const _ as *const kernel::c_types::c_char;
+
+                        {cfg}
+                        #[cfg(MODULE)]
+                        const __{name}_{param_name}_{alias}_alias_name: *const kernel::c_types::c_char = b\"{alias}\\0\" as *const _ as *const kernel::c_types::c_char;
+
+                        {cfg}
+                        #[link_section = \"__param\"]
+                        #[used]
+                        static __{name}_{param_name}_{alias}_alias_struct: __{name}_{param_name}_RacyKernelParam = __{name}_{param_name}_RacyKernelParam(kernel::bindings::kernel_param {{
+                            name: __{name}_{param_name}_{alias}_alias_name,
+                            // SAFETY: `__this_module` is constructed by the kernel at load time and will not be freed until the module is unloaded.
+                            #[cfg(MODULE)]
+                            mod_: unsafe {{ &kernel::bindings::__this_module as *const _ as *mut _ }},
+                            #[cfg(not(MODULE))]
+                            mod_: core::ptr::null_mut(),
+                            ops: unsafe {{ &{ops} }} as *const kernel::bindings::kernel_param_ops,
+                            perm: {permissions},
+                            level: -1,
+                            flags: 0,
+                            __bindgen_anon_1: {kparam}
+                        }});
+                    ",
+                    name = name,
+                    param_name = param_name,
+                    alias = alias,
+                    ops = ops,
+                    permissions = param_permissions,
+                    kparam = kparam,
+                    cfg = param_cfg,
+                ));
+            }
         }
     }
 
@@ -671,6 +1267,9 @@ pub fn module(ts: TokenStream) -> TokenStream {
     let file =
         std::env::var("RUST_MODFILE").expect("Unable to fetch RUST_MODFILE environmental variable");
 
+    // Built-in modules' symbols are global, so make them unique even if two modules share a name.
+    let unique_name = format!("{}_{:016x}", name, fnv1a_hash(file.as_bytes()));
+
     format!(
         "
             /// The module name.
@@ -682,9 +1281,9 @@ pub fn module(ts: TokenStream) -> TokenStream {
 
             // SAFETY: `__this_module` is constructed by the kernel at load time and will not be freed until the module is unloaded.
             #[cfg(MODULE)]
-            static THIS_MODULE: kernel::ThisModule = unsafe {{ kernel::ThisModule::from_ptr(&kernel::bindings::__this_module as *const _ as *mut _) }};
+            static THIS_MODULE: kernel::ThisModule = unsafe {{ kernel::ThisModule::from_ptr(&kernel::bindings::__this_module as *const _ as *mut _, kernel::c_str!(\"{name}\")) }};
             #[cfg(not(MODULE))]
-            static THIS_MODULE: kernel::ThisModule = unsafe {{ kernel::ThisModule::from_ptr(core::ptr::null_mut()) }};
+            static THIS_MODULE: kernel::ThisModule = unsafe {{ kernel::ThisModule::from_ptr(core::ptr::null_mut(), kernel::c_str!(\"{name}\")) }};
 
             // Loadable modules need to export the `{{init,cleanup}}_module` identifiers
             #[cfg(MODULE)]
@@ -705,32 +1304,32 @@ pub fn module(ts: TokenStream) -> TokenStream {
             #[cfg(not(CONFIG_HAVE_ARCH_PREL32_RELOCATIONS))]
             #[link_section = \"{initcall_section}\"]
             #[used]
-            pub static __{name}_initcall: extern \"C\" fn() -> kernel::c_types::c_int = __{name}_init;
+            pub static __{unique_name}_initcall: extern \"C\" fn() -> kernel::c_types::c_int = __{unique_name}_init;
 
             #[cfg(not(MODULE))]
             #[cfg(CONFIG_HAVE_ARCH_PREL32_RELOCATIONS)]
             global_asm!(
                 r#\".section \"{initcall_section}\", \"a\"
-                __{name}_initcall:
-                    .long   __{name}_init - .
+                __{unique_name}_initcall:
+                    .long   __{unique_name}_init - .
                     .previous
                 \"#
             );
 
             #[cfg(not(MODULE))]
             #[no_mangle]
-            pub extern \"C\" fn __{name}_init() -> kernel::c_types::c_int {{
+            pub extern \"C\" fn __{unique_name}_init() -> kernel::c_types::c_int {{
                 __init()
             }}
 
             #[cfg(not(MODULE))]
             #[no_mangle]
-            pub extern \"C\" fn __{name}_exit() {{
+            pub extern \"C\" fn __{unique_name}_exit() {{
                 __exit()
             }}
 
             fn __init() -> kernel::c_types::c_int {{
-                match <{type_} as kernel::KernelModule>::init() {{
+                match <{type_} as kernel::KernelModule>::init_with(THIS_MODULE.name(), &THIS_MODULE) {{
                     Ok(m) => {{
                         unsafe {{
                             __MOD = Some(m);
@@ -760,16 +1359,22 @@ pub fn module(ts: TokenStream) -> TokenStream {
 
             {params_modinfo}
 
+            /// The name, type and permissions of each parameter, for tools that inspect the module.
+            #[cfg(rust_param_schema)]
+            pub const __PARAM_SCHEMA: &[(&str, &str, u32)] = &[{params_schema}];
+
             {generated_array_types}
         ",
         type_ = info.type_,
         name = info.name,
+        unique_name = unique_name,
         author = &build_modinfo_string_optional(&name, "author", info.author.as_deref()),
         description = &build_modinfo_string_optional(&name, "description", info.description.as_deref()),
         license = &build_modinfo_string(&name, "license", &info.license),
         alias = &build_modinfo_string_optional(&name, "alias", info.alias.as_deref()),
         file = &build_modinfo_string_only_builtin(&name, "file", &file),
         params_modinfo = params_modinfo,
+        params_schema = params_schema,
         generated_array_types = generated_array_types,
         initcall_section = ".initcall6.init"
     ).parse().expect("Error parsing formatted string into token stream.")
@@ -778,7 +1383,14 @@ pub fn module(ts: TokenStream) -> TokenStream {
 /// Declares a kernel module that exposes a single misc device.
 ///
 /// The `type` argument should be a type which implements the [`FileOpener`] trait. Also accepts
-/// various forms of kernel metadata.
+/// various forms of kernel metadata, as well as `params`, which are passed on to [`module!`].
+///
+/// Two additional optional keys are accepted after `params`:
+///   - `minor`: the minor number to register the device with; a dynamic one is allocated if
+///     omitted.
+///   - `context`: the context shared by all open files, written as `Type = expression`. The
+///     expression is evaluated at module initialisation time, so it may use `?` to propagate
+///     errors. The `type` must then implement [`FileOpener`] for `Type`.
 ///
 /// [`FileOpener`]: ../kernel/file_operations/trait.FileOpener.html
 ///
@@ -808,13 +1420,23 @@ pub fn module_misc_device(ts: TokenStream) -> TokenStream {
 
     let info = ModuleInfo::parse(&mut it);
 
-    let module = format!("__internal_ModuleFor{}", info.type_);
+    // The type may be a path, so only keep the characters that are valid in an identifier.
+    let module = format!(
+        "__internal_ModuleFor{}",
+        info.type_
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '_')
+            .collect::<String>()
+    );
+    let (context_type, context) = info
+        .context
+        .unwrap_or_else(|| ("()".to_string(), "()".to_string()));
 
     format!(
         "
             #[doc(hidden)]
             struct {module} {{
-                _dev: core::pin::Pin<alloc::boxed::Box<kernel::miscdev::Registration>>,
+                _dev: core::pin::Pin<alloc::boxed::Box<kernel::miscdev::Registration<{context_type}>>>,
             }}
 
             impl kernel::KernelModule for {module} {{
@@ -822,8 +1444,8 @@ pub fn module_misc_device(ts: TokenStream) -> TokenStream {
                     Ok(Self {{
                         _dev: kernel::miscdev::Registration::new_pinned::<{type_}>(
                             kernel::c_str!(\"{name}\"),
-                            None,
-                            (),
+                            {minor},
+                            {context},
                         )?,
                     }})
                 }}
@@ -836,6 +1458,7 @@ pub fn module_misc_device(ts: TokenStream) -> TokenStream {
                 {description}
                 license: b\"{license}\",
                 {alias}
+                {params}
             }}
         ",
         module = module,
@@ -853,6 +1476,16 @@ pub fn module_misc_device(ts: TokenStream) -> TokenStream {
             .alias
             .map(|v| format!("alias: b\"{}\",", v))
             .unwrap_or_else(|| "".to_string()),
+        context_type = context_type,
+        context = context,
+        minor = info
+            .minor
+            .map(|v| format!("Some({})", v))
+            .unwrap_or_else(|| "None".to_string()),
+        params = info
+            .params
+            .map(|v| format!("params: {},", v))
+            .unwrap_or_else(|| "".to_string()),
         license = info.license
     )
     .parse()
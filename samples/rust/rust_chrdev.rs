@@ -1,14 +1,25 @@
 // SPDX-License-Identifier: GPL-2.0
 
 //! Rust character device sample
+//!
+//! Each minor is a read-only, seekable device whose contents are the 256 bytes `0..=255`. Every
+//! open file counts the reads made through it and reports the total when it is closed.
 
 #![no_std]
 #![feature(allocator_api, global_asm)]
 
 use alloc::boxed::Box;
-use core::pin::Pin;
+use core::{
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+};
 use kernel::prelude::*;
-use kernel::{c_str, chrdev, file_operations::FileOperations};
+use kernel::{
+    chrdev,
+    file::{self, File},
+    file_operations::{FileOperations, SeekFrom},
+    io_buffer::IoBufferWriter,
+};
 
 module! {
     type: RustChrdev,
@@ -18,11 +29,51 @@ module! {
     license: b"GPL v2",
 }
 
+const fn contents() -> [u8; 256] {
+    let mut buf = [0u8; 256];
+    let mut i = 0;
+    while i < buf.len() {
+        buf[i] = i as u8;
+        i += 1;
+    }
+    buf
+}
+
+/// The contents of the device.
+static CONTENTS: [u8; 256] = contents();
+
 #[derive(Default)]
-struct RustFile;
+struct RustFile {
+    read_count: AtomicU64,
+}
 
 impl FileOperations for RustFile {
-    kernel::declare_file_operations!();
+    type Wrapper = Box<Self>;
+
+    kernel::declare_file_operations!(read, seek);
+
+    fn release(obj: Box<Self>, _file: &File) {
+        pr_info!(
+            "File closed after {} reads\n",
+            obj.read_count.load(Ordering::Relaxed)
+        );
+    }
+
+    fn read<T: IoBufferWriter>(&self, _: &File, data: &mut T, offset: u64) -> Result<usize> {
+        let len = file::check_offset_len(offset, data.len(), CONTENTS.len() as u64)?;
+        if len == 0 {
+            return Ok(0);
+        }
+        // `offset` is within `CONTENTS`, otherwise `len` would be zero.
+        let offset = offset as usize;
+        data.write_slice(&CONTENTS[offset..offset + len])?;
+        self.read_count.fetch_add(1, Ordering::Relaxed);
+        Ok(len)
+    }
+
+    fn seek(&self, file: &File, offset: SeekFrom) -> Result<u64> {
+        file.fixed_size_seek(offset, CONTENTS.len() as u64)
+    }
 }
 
 struct RustChrdev {
@@ -33,8 +84,7 @@ impl KernelModule for RustChrdev {
     fn init() -> Result<Self> {
         pr_info!("Rust character device sample (init)\n");
 
-        let mut chrdev_reg =
-            chrdev::Registration::new_pinned(c_str!("rust_chrdev"), 0, &THIS_MODULE)?;
+        let mut chrdev_reg = chrdev::Registration::new_pinned(THIS_MODULE.name(), 0, &THIS_MODULE)?;
 
         // Register the same kind of device twice, we're just demonstrating
         // that you can use multiple minors. There are two minors in this case
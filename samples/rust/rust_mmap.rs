@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Rust mmap sample
+//!
+//! Each open file allocates a page, which user space can map with `mmap()`. The page starts with a
+//! greeting and is otherwise zeroed.
+
+#![no_std]
+#![feature(allocator_api, global_asm)]
+
+use alloc::boxed::Box;
+use kernel::prelude::*;
+use kernel::{
+    file::File,
+    file_operations::{FileOpener, FileOperations},
+    mm::VmArea,
+    pages::Pages,
+    Error, PAGE_SIZE,
+};
+
+const GREETING: &[u8] = b"Hello from the Rust mmap sample!\n";
+
+struct MmapFile {
+    page: Pages<0>,
+}
+
+// SAFETY: The page is only written to before the file is shared, and afterwards only mapped into
+// user space, which `vm_insert_page` synchronises. Freeing it from any thread is fine.
+unsafe impl Send for MmapFile {}
+
+// SAFETY: See the `Send` implementation above.
+unsafe impl Sync for MmapFile {}
+
+impl FileOpener<()> for MmapFile {
+    fn open(_: &(), _file: &File) -> Result<Box<Self>> {
+        let page = Pages::<0>::new()?;
+        // SAFETY: `GREETING` is valid for its length, and it has no padding that could leak.
+        unsafe { page.write(GREETING.as_ptr(), 0, GREETING.len()) }?;
+        Ok(Box::try_new(MmapFile { page })?)
+    }
+}
+
+impl FileOperations for MmapFile {
+    kernel::declare_file_operations!(mmap);
+
+    fn mmap(&self, _file: &File, vma: &mut VmArea) -> Result {
+        // Only the single page at the start of the file can be mapped.
+        if vma.pgoff() != 0 || vma.end() - vma.start() != PAGE_SIZE {
+            return Err(Error::EINVAL);
+        }
+        let start = vma.start();
+        self.page.insert_page(vma, start)
+    }
+}
+
+module_misc_device! {
+    type: MmapFile,
+    name: b"rust_mmap",
+    author: b"Rust for Linux Contributors",
+    description: b"Rust mmap sample",
+    license: b"GPL v2",
+}
@@ -27,6 +27,7 @@ module! {
         my_str: str {
             default: b"default str val",
             permissions: 0o644,
+            racy_ok: true,
             description: b"Example of a string param",
         },
         my_usize: usize {
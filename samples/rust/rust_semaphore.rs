@@ -6,12 +6,19 @@
 //!
 //! The count is incremented by writes to the device. A write of `n` bytes results in an increment
 //! of `n`. It is decremented by reads; each read results in the count being decremented by 1. If
-//! the count is already zero, a read will block until another write increments it.
+//! the count is already zero, a read will block until another write increments it, or fail with
+//! `EAGAIN` if the file was opened with `O_NONBLOCK`. `poll` reports the device as readable
+//! whenever the count is non-zero.
 //!
 //! This can be used in user space from the shell for example  as follows (assuming a node called
 //! `semaphore`): `cat semaphore` decrements the count by 1 (waiting for it to become non-zero
 //! before decrementing); `echo -n 123 > semaphore` increments the semaphore by 3, potentially
-//! unblocking up to 3 blocked readers.
+//! unblocking up to 3 blocked readers. Writes that would take the count above the `max_count`
+//! module parameter fail with `ENOSPC`. If the `max_write` module parameter is non-zero, a single
+//! write accepts at most that many bytes and returns the number accepted, like a short write.
+//!
+//! Besides getting and setting the per-file read count, the `IOCTL_GET_INFO` ioctl returns the
+//! current count and the highest count seen so far, both as `u64`s.
 
 #![no_std]
 #![feature(allocator_api, global_asm)]
@@ -22,10 +29,10 @@ use core::{
     sync::atomic::{AtomicU64, Ordering},
 };
 use kernel::{
-    c_str, condvar_init, declare_file_operations,
+    bindings, condvar_init, declare_file_operations,
     file::File,
-    file_operations::{FileOpener, FileOperations, IoctlCommand, IoctlHandler},
-    io_buffer::{IoBufferReader, IoBufferWriter},
+    file_operations::{FileOpener, FileOperations, IoctlCommand, IoctlHandler, PollTable},
+    io_buffer::{IoBufferReader, IoBufferWriter, WritableToBytes},
     miscdev::Registration,
     mutex_init,
     prelude::*,
@@ -40,6 +47,18 @@ module! {
     author: b"Rust for Linux Contributors",
     description: b"Rust semaphore sample",
     license: b"GPL v2",
+    params: {
+        max_count: usize {
+            default: 4096,
+            permissions: 0o444,
+            description: b"Maximum value of the semaphore count",
+        },
+        max_write: usize {
+            default: 0,
+            permissions: 0o444,
+            description: b"Maximum number of bytes accepted by a single write (0 for no limit)",
+        },
+    },
 }
 
 struct SemaphoreInner {
@@ -47,6 +66,15 @@ struct SemaphoreInner {
     max_seen: usize,
 }
 
+impl SemaphoreInner {
+    /// Checks that the count has never exceeded the maximum count observed so far.
+    ///
+    /// This must hold whenever the lock is released.
+    fn check_invariant(&self) {
+        debug_assert!(self.count <= self.max_seen);
+    }
+}
+
 struct Semaphore {
     changed: CondVar,
     inner: Mutex<SemaphoreInner>,
@@ -58,20 +86,24 @@ struct FileState {
 }
 
 impl FileState {
-    fn consume(&self) -> Result {
+    fn consume(&self, file: &File) -> Result {
         let mut inner = self.shared.inner.lock();
         while inner.count == 0 {
+            if !file.is_blocking() {
+                return Err(Error::EAGAIN);
+            }
             if self.shared.changed.wait(&mut inner) {
                 return Err(Error::EINTR);
             }
         }
         inner.count -= 1;
+        inner.check_invariant();
         Ok(())
     }
 }
 
 impl FileOpener<Arc<Semaphore>> for FileState {
-    fn open(shared: &Arc<Semaphore>) -> Result<Box<Self>> {
+    fn open(shared: &Arc<Semaphore>, _file: &File) -> Result<Box<Self>> {
         Ok(Box::try_new(Self {
             read_count: AtomicU64::new(0),
             shared: shared.clone(),
@@ -82,34 +114,61 @@ impl FileOpener<Arc<Semaphore>> for FileState {
 impl FileOperations for FileState {
     type Wrapper = Box<Self>;
 
-    declare_file_operations!(read, write, ioctl);
+    declare_file_operations!(read, write, ioctl, poll);
 
-    fn read<T: IoBufferWriter>(&self, _: &File, data: &mut T, offset: u64) -> Result<usize> {
+    fn read<T: IoBufferWriter>(&self, file: &File, data: &mut T, offset: u64) -> Result<usize> {
         if data.is_empty() || offset > 0 {
             return Ok(0);
         }
-        self.consume()?;
+        self.consume(file)?;
         data.write_slice(&[0u8; 1])?;
-        self.read_count.fetch_add(1, Ordering::Relaxed);
+        // The read count saturates rather than wrapping around.
+        let _ = self
+            .read_count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                Some(count.saturating_add(1))
+            });
         Ok(1)
     }
 
     fn write<T: IoBufferReader>(&self, _: &File, data: &mut T, _offset: u64) -> Result<usize> {
+        let len = match *max_write.read() {
+            0 => data.len(),
+            max => data.len().min(max),
+        };
         {
             let mut inner = self.shared.inner.lock();
-            inner.count = inner.count.saturating_add(data.len());
+            inner.count = inner
+                .count
+                .checked_add(len)
+                .filter(|count| count <= max_count.read())
+                .ok_or(Error::ENOSPC)?;
             if inner.count > inner.max_seen {
                 inner.max_seen = inner.count;
             }
+            inner.check_invariant();
         }
 
         self.shared.changed.notify_all();
-        Ok(data.len())
+        Ok(len)
     }
 
     fn ioctl(&self, file: &File, cmd: &mut IoctlCommand) -> Result<i32> {
         cmd.dispatch(self, file)
     }
+
+    fn poll(&self, file: &File, table: &PollTable) -> Result<u32> {
+        // SAFETY: `changed` is owned by the shared semaphore, which is also held by the
+        // registration and therefore outlives every open file.
+        unsafe { table.register_wait(file, &self.shared.changed) };
+
+        // Writes never block; reads only block when the count is zero.
+        let mut mask = bindings::POLLOUT | bindings::POLLWRNORM;
+        if self.shared.inner.lock().count > 0 {
+            mask |= bindings::POLLIN | bindings::POLLRDNORM;
+        }
+        Ok(mask)
+    }
 }
 
 struct RustSemaphore {
@@ -140,7 +199,7 @@ impl KernelModule for RustSemaphore {
         mutex_init!(Pin::new_unchecked(&sema.inner), "Semaphore::inner");
 
         Ok(Self {
-            _dev: Registration::new_pinned::<FileState>(c_str!("rust_semaphore"), None, sema)?,
+            _dev: Registration::new_pinned::<FileState>(THIS_MODULE.name(), None, sema)?,
         })
     }
 }
@@ -153,6 +212,17 @@ impl Drop for RustSemaphore {
 
 const IOCTL_GET_READ_COUNT: u32 = 0x80086301;
 const IOCTL_SET_READ_COUNT: u32 = 0x40086301;
+const IOCTL_GET_INFO: u32 = 0x80106302;
+
+/// The state of the semaphore, as returned by `IOCTL_GET_INFO`.
+#[repr(C)]
+struct SemaphoreInfo {
+    count: u64,
+    max_seen: u64,
+}
+
+// SAFETY: `SemaphoreInfo` only contains `u64` fields, so it has no padding.
+unsafe impl WritableToBytes for SemaphoreInfo {}
 
 impl IoctlHandler for FileState {
     fn read(&self, _: &File, cmd: u32, writer: &mut UserSlicePtrWriter) -> Result<i32> {
@@ -161,6 +231,17 @@ impl IoctlHandler for FileState {
                 writer.write(&self.read_count.load(Ordering::Relaxed))?;
                 Ok(0)
             }
+            IOCTL_GET_INFO => {
+                let info = {
+                    let inner = self.shared.inner.lock();
+                    SemaphoreInfo {
+                        count: inner.count as u64,
+                        max_seen: inner.max_seen as u64,
+                    }
+                };
+                writer.write(&info)?;
+                Ok(0)
+            }
             _ => Err(Error::EINVAL),
         }
     }